@@ -1,6 +1,14 @@
+use std::path::PathBuf;
+
 pub enum HttpBody {
     Text(String),
     Binary(Vec<u8>),
+    Chunked(Vec<u8>),
+    /// A file to be streamed straight off disk in fixed-size chunks, so large
+    /// uncompressed `/files/` downloads never need their full contents buffered
+    /// in memory. When a content coding is negotiated, the file is read in full
+    /// before it is compressed.
+    File(PathBuf),
 }
 
 impl HttpBody {
@@ -8,6 +16,12 @@ impl HttpBody {
         match self {
             HttpBody::Text(text) => text.as_bytes(),
             HttpBody::Binary(bytes) => bytes,
+            HttpBody::Chunked(bytes) => bytes,
+            HttpBody::File(_) => unreachable!("file bodies are streamed directly, not buffered"),
         }
     }
+
+    pub fn is_chunked(&self) -> bool {
+        matches!(self, HttpBody::Chunked(_) | HttpBody::File(_))
+    }
 }
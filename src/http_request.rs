@@ -1,12 +1,19 @@
 use crate::http_body::HttpBody;
 use crate::http_headers::HttpHeaders;
 use anyhow::{anyhow, Context, Result};
+use flate2::read::{DeflateDecoder, GzDecoder};
 use std::io::{BufRead, BufReader, Read};
 use std::net::TcpStream;
 
+/// Upper bound on a single request body (or chunk), so a malicious
+/// `Content-Length`/chunk-size header can't make us allocate an
+/// unreasonable amount of memory before we've even read the data.
+const MAX_BODY_SIZE: usize = 100 * 1024 * 1024;
+
 pub struct HttpRequest {
     pub(crate) method: String,
     pub(crate) path: String,
+    pub(crate) version: String,
     pub(crate) headers: HttpHeaders,
     pub(crate) body: Option<HttpBody>,
 }
@@ -14,43 +21,76 @@ pub struct HttpRequest {
 struct RequestLine {
     method: String,
     path: String,
+    version: String,
 }
 
-pub fn parse(stream: &mut TcpStream) -> Result<HttpRequest> {
+/// Parses a single request off `reader`. Returns `Ok(None)` when the peer
+/// closed the connection before sending another request line, so callers can
+/// reuse the same reader across a keep-alive connection without losing any
+/// bytes buffered between pipelined requests.
+pub fn parse(reader: &mut BufReader<TcpStream>) -> Result<Option<HttpRequest>> {
+    let request_line = match parse_request_line(reader).context("Failed to parse request line")? {
+        Some(request_line) => request_line,
+        None => return Ok(None),
+    };
+
     let mut request = HttpRequest {
-        method: String::new(),
-        path: String::new(),
+        method: request_line.method,
+        path: request_line.path,
+        version: request_line.version,
         headers: HttpHeaders::new(),
         body: None,
     };
-
-    let mut reader = BufReader::new(&*stream);
-    let request_line = parse_request_line(&mut reader).context("Failed to parse request line")?;
-
-    request.method = request_line.method;
-    request.path = request_line.path;
     println!("Received {} request for {}", request.method, request.path);
 
-    request.headers = parse_headers(&mut reader).context("Failed to parse headers")?;
+    request.headers = parse_headers(reader).context("Failed to parse headers")?;
 
+    let transfer_encoding = request.headers.get("transfer-encoding");
     let content_length = request.headers.get("content-length");
-    if content_length.is_some() {
+
+    let body = if transfer_encoding.is_some_and(|encoding| encoding.trim().eq_ignore_ascii_case("chunked")) {
+        Some(parse_chunked_body(reader).context("Failed to parse chunked body")?)
+    } else if content_length.is_some() {
         let content_length: usize = content_length
             .context("Unable to read Content-Length header")?
             .parse()
             .context("Unable to parse Content-Length header")?;
-        let body = parse_body(&mut reader, content_length).context("Failed to parse body")?;
+        if content_length > MAX_BODY_SIZE {
+            return Err(anyhow!(
+                "Content-Length {} exceeds maximum of {} bytes",
+                content_length,
+                MAX_BODY_SIZE
+            ));
+        }
+        Some(parse_body(reader, content_length).context("Failed to parse body")?)
+    } else {
+        None
+    };
+
+    if let Some(body) = body {
+        let body = decompress_body(body, request.headers.get("content-encoding"))
+            .context("Failed to decompress body")?;
         request.body = Some(body);
     }
 
-    Ok(request)
+    Ok(Some(request))
 }
 
-fn parse_request_line(reader: &mut BufReader<&TcpStream>) -> Result<RequestLine> {
+fn parse_request_line(reader: &mut BufReader<TcpStream>) -> Result<Option<RequestLine>> {
     let mut buffer = Vec::new();
-    reader
-        .read_until(b'\n', &mut buffer)
-        .context("Failed to read request line")?;
+    let bytes_read = match reader.read_until(b'\n', &mut buffer) {
+        Ok(bytes_read) => bytes_read,
+        // The socket sat idle past the keep-alive timeout without a byte of a
+        // new request arriving - treat it the same as the peer closing the
+        // connection rather than a parse error, so we close silently instead
+        // of firing a 400 at a connection nobody is using anymore.
+        Err(e) if is_timeout(&e) && buffer.is_empty() => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read request line"),
+    };
+
+    if bytes_read == 0 {
+        return Ok(None);
+    }
 
     let request_line = String::from_utf8(buffer)
         .context("Request line is not valid UTF-8")?
@@ -59,16 +99,24 @@ fn parse_request_line(reader: &mut BufReader<&TcpStream>) -> Result<RequestLine>
 
     let parts: Vec<&str> = request_line.split(" ").collect();
     if parts.len() == 3 {
-        Ok(RequestLine {
+        Ok(Some(RequestLine {
             method: parts.get(0).unwrap().to_string(),
             path: parts.get(1).unwrap().to_string(),
-        })
+            version: parts.get(2).unwrap().to_string(),
+        }))
     } else {
         Err(anyhow!("Invalid request line: {}", request_line))
     }
 }
 
-fn parse_headers(reader: &mut BufReader<&TcpStream>) -> Result<HttpHeaders> {
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+fn parse_headers(reader: &mut BufReader<TcpStream>) -> Result<HttpHeaders> {
     let mut headers = HttpHeaders::new();
     let mut buffer = String::new();
 
@@ -98,7 +146,7 @@ fn parse_headers(reader: &mut BufReader<&TcpStream>) -> Result<HttpHeaders> {
     Ok(headers)
 }
 
-fn parse_body(reader: &mut BufReader<&TcpStream>, content_length: usize) -> Result<HttpBody> {
+fn parse_body(reader: &mut BufReader<TcpStream>, content_length: usize) -> Result<HttpBody> {
     let mut buffer = vec![0; content_length];
 
     reader
@@ -107,3 +155,110 @@ fn parse_body(reader: &mut BufReader<&TcpStream>, content_length: usize) -> Resu
 
     Ok(HttpBody::Binary(buffer))
 }
+
+fn parse_chunked_body(reader: &mut BufReader<TcpStream>) -> Result<HttpBody> {
+    let mut body = Vec::new();
+
+    loop {
+        let mut size_line = String::new();
+        reader
+            .read_line(&mut size_line)
+            .context("Failed to read chunk size")?;
+
+        let size_line = size_line.trim();
+        let size = size_line.split(';').next().unwrap_or(size_line).trim();
+        let chunk_size =
+            usize::from_str_radix(size, 16).context("Invalid chunk size")?;
+
+        if chunk_size == 0 {
+            break;
+        }
+
+        if chunk_size > MAX_BODY_SIZE || body.len() + chunk_size > MAX_BODY_SIZE {
+            return Err(anyhow!(
+                "Chunked body exceeds maximum of {} bytes",
+                MAX_BODY_SIZE
+            ));
+        }
+
+        let mut chunk = vec![0; chunk_size];
+        reader
+            .read_exact(&mut chunk)
+            .context("Failed to read chunk data")?;
+        body.extend_from_slice(&chunk);
+
+        let mut crlf = [0u8; 2];
+        reader
+            .read_exact(&mut crlf)
+            .context("Failed to read chunk terminator")?;
+    }
+
+    loop {
+        let mut trailer_line = String::new();
+        let bytes_read = reader
+            .read_line(&mut trailer_line)
+            .context("Failed to read chunk trailer")?;
+
+        if bytes_read == 0 || trailer_line.trim().is_empty() {
+            break;
+        }
+    }
+
+    Ok(HttpBody::Binary(body))
+}
+
+fn decompress_body(body: HttpBody, content_encoding: Option<&String>) -> Result<HttpBody> {
+    let content_encoding = match content_encoding {
+        Some(content_encoding) => content_encoding,
+        None => return Ok(body),
+    };
+
+    let mut data = body.as_bytes().to_vec();
+
+    for coding in content_encoding.split(',').rev() {
+        let coding = coding.trim().to_lowercase();
+        if coding.is_empty() || coding == "identity" {
+            continue;
+        }
+
+        data = match coding.as_str() {
+            "gzip" => decompress_gzip(&data)?,
+            "deflate" => decompress_deflate(&data)?,
+            "br" => decompress_brotli(&data)?,
+            "zstd" => decompress_zstd(&data)?,
+            other => return Err(anyhow!("Unsupported Content-Encoding: {}", other)),
+        };
+    }
+
+    Ok(HttpBody::Binary(data))
+}
+
+fn decompress_gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = GzDecoder::new(data);
+    let mut decoded = Vec::new();
+    decoder
+        .read_to_end(&mut decoded)
+        .context("Failed to decompress gzip body")?;
+    Ok(decoded)
+}
+
+fn decompress_deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = DeflateDecoder::new(data);
+    let mut decoded = Vec::new();
+    decoder
+        .read_to_end(&mut decoded)
+        .context("Failed to decompress deflate body")?;
+    Ok(decoded)
+}
+
+fn decompress_brotli(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(data, 4096)
+        .read_to_end(&mut decoded)
+        .context("Failed to decompress brotli body")?;
+    Ok(decoded)
+}
+
+fn decompress_zstd(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).context("Failed to decompress zstd body")
+}
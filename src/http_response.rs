@@ -4,9 +4,11 @@ use crate::http_status::HttpStatus;
 use anyhow::{Context, Result};
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use std::collections::HashMap;
 use std::fmt;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::net::TcpStream;
+use std::path::Path;
 
 pub struct HttpResponse {
     pub(crate) status: HttpStatus,
@@ -14,13 +16,24 @@ pub struct HttpResponse {
     pub(crate) body: Option<HttpBody>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum ContentEncoding {
     None,
+    NotAcceptable,
     Deflate,
     Gzip,
+    Br,
+    Zstd,
 }
 
+// Server preference order when several codings tie on quality, best-compressing first.
+const SUPPORTED_ENCODINGS: [(&str, ContentEncoding); 4] = [
+    ("br", ContentEncoding::Br),
+    ("zstd", ContentEncoding::Zstd),
+    ("gzip", ContentEncoding::Gzip),
+    ("deflate", ContentEncoding::Deflate),
+];
+
 impl fmt::Display for ContentEncoding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -32,12 +45,18 @@ pub fn send(
     request_headers: HttpHeaders,
     mut response: HttpResponse,
 ) -> Result<()> {
-    send_status_line(stream, &mut response.status)?;
-
     let content_encoding = determine_content_encoding(&request_headers);
-    compress_body(&mut response, &content_encoding).context("Failed to compress body")?;
+    if content_encoding == ContentEncoding::NotAcceptable && response.body.is_some() {
+        response.status = HttpStatus::NOT_ACCEPTABLE;
+        response.body = None;
+    } else if content_encoding != ContentEncoding::NotAcceptable {
+        compress_body(&mut response, &content_encoding).context("Failed to compress body")?;
+    }
+
+    send_status_line(stream, &mut response.status)?;
 
     set_content_length_header(&mut response);
+    set_transfer_encoding_header(&mut response);
     set_content_type_header(&mut response);
 
     send_headers(stream, &mut response.headers)?;
@@ -56,25 +75,33 @@ fn compress_body(response: &mut HttpResponse, content_encoding: &ContentEncoding
         .as_ref()
         .context("Failed to take response body")?;
 
-    let compressed_data: Vec<u8>;
-
-    match content_encoding {
-        ContentEncoding::None => return Ok(()),
-        ContentEncoding::Gzip => {
-            compressed_data = match body {
-                HttpBody::Text(text) => compress_gzip(text.as_bytes())?,
-                HttpBody::Binary(bytes) => compress_gzip(&bytes)?,
-            };
-        }
-        ContentEncoding::Deflate => {
-            compressed_data = match body {
-                HttpBody::Text(text) => compress_deflate(text.as_bytes())?,
-                HttpBody::Binary(bytes) => compress_deflate(&bytes)?,
-            };
-        }
+    if *content_encoding == ContentEncoding::None || *content_encoding == ContentEncoding::NotAcceptable {
+        return Ok(());
     }
 
-    response.body = Some(HttpBody::Binary(compressed_data));
+    let is_chunked = body.is_chunked();
+
+    // Actual compression needs the whole representation in memory regardless
+    // of source, so a `File` body is read in full here - unlike the identity
+    // path, which streams it straight off disk without ever buffering it.
+    let data = match body {
+        HttpBody::File(path) => std::fs::read(path).context("Failed to read file for compression")?,
+        _ => body.as_bytes().to_vec(),
+    };
+
+    let compressed_data = match content_encoding {
+        ContentEncoding::Gzip => compress_gzip(&data)?,
+        ContentEncoding::Deflate => compress_deflate(&data)?,
+        ContentEncoding::Br => compress_brotli(&data)?,
+        ContentEncoding::Zstd => compress_zstd(&data)?,
+        ContentEncoding::None | ContentEncoding::NotAcceptable => unreachable!(),
+    };
+
+    response.body = Some(if is_chunked {
+        HttpBody::Chunked(compressed_data)
+    } else {
+        HttpBody::Binary(compressed_data)
+    });
     set_content_encoding_header(response, content_encoding);
 
     Ok(())
@@ -100,6 +127,27 @@ fn compress_gzip(data: &[u8]) -> Result<Vec<u8>> {
         .context("Failed to finish gzip compression")
 }
 
+fn compress_brotli(data: &[u8]) -> Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+    encoder
+        .write_all(data)
+        .context("Failed to write data to brotli encoder")?;
+    drop(encoder);
+    Ok(compressed)
+}
+
+fn compress_zstd(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder =
+        zstd::Encoder::new(Vec::new(), 3).context("Failed to create zstd encoder")?;
+    encoder
+        .write_all(data)
+        .context("Failed to write data to zstd encoder")?;
+    encoder
+        .finish()
+        .context("Failed to finish zstd compression")
+}
+
 fn set_content_encoding_header(response: &mut HttpResponse, content_encoding: &ContentEncoding) {
     if *content_encoding == ContentEncoding::None {
         return;
@@ -112,6 +160,10 @@ fn set_content_encoding_header(response: &mut HttpResponse, content_encoding: &C
 }
 
 fn set_content_length_header(response: &mut HttpResponse) {
+    if matches!(response.body, Some(HttpBody::Chunked(_)) | Some(HttpBody::File(_))) {
+        return;
+    }
+
     let content_lemgth = determine_content_length(&response.body);
     if content_lemgth > 0 {
         response
@@ -120,6 +172,14 @@ fn set_content_length_header(response: &mut HttpResponse) {
     }
 }
 
+fn set_transfer_encoding_header(response: &mut HttpResponse) {
+    if matches!(response.body, Some(HttpBody::Chunked(_)) | Some(HttpBody::File(_))) {
+        response
+            .headers
+            .insert("Transfer-Encoding".to_string(), "chunked".to_string());
+    }
+}
+
 fn set_content_type_header(response: &mut HttpResponse) {
     if response.headers.get("Content-Type").is_some() {
         return;
@@ -132,23 +192,62 @@ fn set_content_type_header(response: &mut HttpResponse) {
 }
 
 fn determine_content_encoding(request_headers: &HttpHeaders) -> ContentEncoding {
-    let accept_encoding = request_headers.get("accept-encoding");
-    if accept_encoding.is_none() {
-        return ContentEncoding::None;
-    }
+    let accept_encoding = match request_headers.get("accept-encoding") {
+        Some(accept_encoding) => accept_encoding,
+        None => return ContentEncoding::None,
+    };
+
+    let mut qualities: HashMap<String, f32> = HashMap::new();
+    let mut wildcard_quality: Option<f32> = None;
+
+    for coding in accept_encoding.split(',') {
+        let coding = coding.trim();
+        if coding.is_empty() {
+            continue;
+        }
 
-    let encodings = accept_encoding.unwrap().split(",");
+        let mut parts = coding.splitn(2, ";q=");
+        let name = parts.next().unwrap().trim().to_lowercase();
+        let quality = parts
+            .next()
+            .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+
+        if name == "*" {
+            wildcard_quality = Some(quality);
+        } else {
+            qualities.insert(name, quality);
+        }
+    }
 
-    for encoding in encodings {
-        if encoding.trim().to_lowercase() == "gzip" {
-            return ContentEncoding::Gzip;
+    let mut best_supported: Option<(ContentEncoding, f32)> = None;
+    for (name, encoding) in SUPPORTED_ENCODINGS {
+        let quality = quality_of(name, &qualities, wildcard_quality);
+        if quality <= 0.0 {
+            continue;
         }
-        if encoding.trim().to_lowercase() == "deflate" {
-            return ContentEncoding::Deflate;
+        if best_supported.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best_supported = Some((encoding, quality));
         }
     }
 
-    ContentEncoding::None
+    match best_supported {
+        Some((encoding, _)) => encoding,
+        None if quality_of("identity", &qualities, wildcard_quality) > 0.0 => ContentEncoding::None,
+        None => ContentEncoding::NotAcceptable,
+    }
+}
+
+fn quality_of(coding: &str, qualities: &HashMap<String, f32>, wildcard_quality: Option<f32>) -> f32 {
+    if let Some(&quality) = qualities.get(coding) {
+        return quality;
+    }
+
+    if coding == "identity" {
+        return wildcard_quality.unwrap_or(1.0);
+    }
+
+    wildcard_quality.unwrap_or(0.0)
 }
 
 fn determine_content_length(body: &Option<HttpBody>) -> usize {
@@ -156,6 +255,8 @@ fn determine_content_length(body: &Option<HttpBody>) -> usize {
         None => 0,
         Some(HttpBody::Text(text)) => text.len(),
         Some(HttpBody::Binary(bytes)) => bytes.len(),
+        Some(HttpBody::Chunked(bytes)) => bytes.len(),
+        Some(HttpBody::File(_)) => 0,
     }
 }
 
@@ -164,6 +265,8 @@ fn determine_content_type(body: &Option<HttpBody>) -> String {
         None => "text/plain".to_string(),
         Some(HttpBody::Text(_)) => "text/plain".to_string(),
         Some(HttpBody::Binary(_)) => "application/octet-stream".to_string(),
+        Some(HttpBody::Chunked(_)) => "application/octet-stream".to_string(),
+        Some(HttpBody::File(_)) => "application/octet-stream".to_string(),
     }
 }
 
@@ -174,10 +277,60 @@ fn send_body(stream: &mut TcpStream, body: &Option<HttpBody>) -> Result<()> {
             .write_all(text.as_bytes())
             .context("Failed to send body")?,
         Some(HttpBody::Binary(bytes)) => stream.write_all(&bytes).context("Failed to send body")?,
+        Some(HttpBody::Chunked(bytes)) => send_chunked_body(stream, bytes)?,
+        Some(HttpBody::File(path)) => send_chunked_file(stream, path)?,
     };
     Ok(())
 }
 
+fn send_chunked_file(stream: &mut TcpStream, path: &Path) -> Result<()> {
+    const CHUNK_SIZE: usize = 8192;
+
+    let mut file = std::fs::File::open(path).context("Failed to open file")?;
+    let mut buffer = [0u8; CHUNK_SIZE];
+
+    loop {
+        let bytes_read = file.read(&mut buffer).context("Failed to read file chunk")?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        write!(stream, "{:x}\r\n", bytes_read).context("Failed to write chunk size")?;
+        stream
+            .write_all(&buffer[..bytes_read])
+            .context("Failed to write chunk data")?;
+        stream
+            .write_all(b"\r\n")
+            .context("Failed to write chunk terminator")?;
+    }
+
+    stream
+        .write_all(b"0\r\n\r\n")
+        .context("Failed to write final chunk")?;
+
+    Ok(())
+}
+
+fn send_chunked_body(stream: &mut TcpStream, bytes: &[u8]) -> Result<()> {
+    const CHUNK_SIZE: usize = 8192;
+
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        write!(stream, "{:x}\r\n", chunk.len()).context("Failed to write chunk size")?;
+        stream
+            .write_all(chunk)
+            .context("Failed to write chunk data")?;
+        stream
+            .write_all(b"\r\n")
+            .context("Failed to write chunk terminator")?;
+    }
+
+    stream
+        .write_all(b"0\r\n\r\n")
+        .context("Failed to write final chunk")?;
+
+    Ok(())
+}
+
 fn send_status_line(stream: &mut TcpStream, status: &HttpStatus) -> Result<()> {
     let status_line = format!("HTTP/1.1 {} {}\r\n", status.code, status.text);
     stream
@@ -8,6 +8,14 @@ impl HttpStatus {
         code: 200,
         text: "OK",
     };
+    pub(crate) const PARTIAL_CONTENT: HttpStatus = HttpStatus {
+        code: 206,
+        text: "Partial Content",
+    };
+    pub(crate) const NOT_MODIFIED: HttpStatus = HttpStatus {
+        code: 304,
+        text: "Not Modified",
+    };
     pub(crate) const BAD_REQUEST: HttpStatus = HttpStatus {
         code: 400,
         text: "Bad Request",
@@ -20,6 +28,14 @@ impl HttpStatus {
         code: 405,
         text: "Method Not Allowed",
     };
+    pub(crate) const NOT_ACCEPTABLE: HttpStatus = HttpStatus {
+        code: 406,
+        text: "Not Acceptable",
+    };
+    pub(crate) const RANGE_NOT_SATISFIABLE: HttpStatus = HttpStatus {
+        code: 416,
+        text: "Range Not Satisfiable",
+    };
     pub(crate) const INTERNAL_SERVER_ERROR: HttpStatus = HttpStatus {
         code: 500,
         text: "Internal Server Error",
@@ -6,10 +6,13 @@ use http_request::HttpRequest;
 use http_response::HttpResponse;
 use http_status::HttpStatus;
 use std::fmt::Debug;
-use std::io::Write;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::OnceLock;
 use std::thread;
+use std::time::Duration;
+
+const KEEP_ALIVE_READ_TIMEOUT: Duration = Duration::from_secs(30);
 
 mod http_body;
 mod http_headers;
@@ -36,10 +39,10 @@ fn main() {
 
     for stream in listener.incoming() {
         match stream {
-            Ok(mut stream) => {
+            Ok(stream) => {
                 thread::spawn(move || {
                     println!("accepted new connection");
-                    if let Err(e) = handle_connection(&mut stream) {
+                    if let Err(e) = handle_connection(stream) {
                         eprintln!("Error handling connection: {}", e);
                     }
                 });
@@ -51,33 +54,68 @@ fn main() {
     }
 }
 
-fn handle_connection(stream: &mut TcpStream) -> Result<()> {
-    let request = match http_request::parse(stream) {
-        Ok(request) => request,
-        Err(_) => {
-            http_response::send(
-                stream,
-                HttpResponse {
-                    status: HttpStatus::BAD_REQUEST,
-                    headers: HttpHeaders::new(),
-                    body: None,
-                },
-            )
+fn handle_connection(stream: TcpStream) -> Result<()> {
+    stream
+        .set_read_timeout(Some(KEEP_ALIVE_READ_TIMEOUT))
+        .context("Failed to set read timeout")?;
+    let mut write_stream = stream.try_clone().context("Failed to clone stream")?;
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let request = match http_request::parse(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(_) => {
+                http_response::send(
+                    &mut write_stream,
+                    HttpHeaders::new(),
+                    HttpResponse {
+                        status: HttpStatus::BAD_REQUEST,
+                        headers: HttpHeaders::new(),
+                        body: None,
+                    },
+                )
+                .context("Failed to send response")?;
+                break;
+            }
+        };
+
+        let keep_alive = is_keep_alive(&request);
+
+        let mut response = handle_request(&request).unwrap_or_else(|_| HttpResponse {
+            status: HttpStatus::INTERNAL_SERVER_ERROR,
+            headers: HttpHeaders::new(),
+            body: None,
+        });
+
+        response.headers.insert(
+            "Connection".to_string(),
+            if keep_alive { "keep-alive" } else { "close" }.to_string(),
+        );
+
+        http_response::send(&mut write_stream, request.headers, response)
             .context("Failed to send response")?;
-            return Ok(());
-        }
-    };
 
-    let response = handle_request(&request).unwrap_or_else(|_| HttpResponse {
-        status: HttpStatus::INTERNAL_SERVER_ERROR,
-        headers: HttpHeaders::new(),
-        body: None,
-    });
+        if !keep_alive {
+            break;
+        }
+    }
 
-    http_response::send(stream, response).context("Failed to send response")?;
     Ok(())
 }
 
+fn is_keep_alive(request: &HttpRequest) -> bool {
+    match request
+        .headers
+        .get("connection")
+        .map(|value| value.trim().to_lowercase())
+    {
+        Some(value) if value == "close" => false,
+        Some(value) if value == "keep-alive" => true,
+        _ => request.version.trim() != "HTTP/1.0",
+    }
+}
+
 fn handle_request(request: &HttpRequest) -> Result<HttpResponse> {
     match request.method.to_uppercase().as_str() {
         "GET" => {
@@ -152,27 +190,184 @@ fn handle_get_files(request: &HttpRequest) -> Result<HttpResponse> {
         .context("Failed to strip prefix")?;
     let file_path = format!("{}/{}", files_directory, file_name);
 
-    match std::fs::read(&file_path) {
-        Ok(contents) => Ok(HttpResponse {
-            status: HttpStatus::OK,
-            headers: HttpHeaders::from([(
-                "Content-Type".to_string(),
-                "application/octet-stream".to_string(),
-            )]),
-            body: Some(HttpBody::Binary(contents)),
-        }),
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                Ok(HttpResponse {
-                    status: HttpStatus::NOT_FOUND,
-                    headers: HttpHeaders::new(),
-                    body: None,
-                })
-            } else {
-                Err(e).context("Failed to read file")?
-            }
+    let metadata = match std::fs::metadata(&file_path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(HttpResponse {
+                status: HttpStatus::NOT_FOUND,
+                headers: HttpHeaders::new(),
+                body: None,
+            });
+        }
+        Err(e) => Err(e).context("Failed to stat file")?,
+    };
+
+    let last_modified = metadata
+        .modified()
+        .context("Failed to read file modification time")?;
+    let etag = compute_etag(metadata.len(), last_modified)?;
+
+    if not_modified(request, &etag, last_modified) {
+        return Ok(HttpResponse {
+            status: HttpStatus::NOT_MODIFIED,
+            headers: HttpHeaders::from([
+                ("ETag".to_string(), etag),
+                ("Last-Modified".to_string(), httpdate::fmt_http_date(last_modified)),
+            ]),
+            body: None,
+        });
+    }
+
+    let headers = HttpHeaders::from([
+        ("Content-Type".to_string(), determine_file_content_type(file_name)),
+        ("ETag".to_string(), etag),
+        ("Last-Modified".to_string(), httpdate::fmt_http_date(last_modified)),
+        ("Accept-Ranges".to_string(), "bytes".to_string()),
+    ]);
+
+    if let Some(range) = request.headers.get("range") {
+        return handle_range(&file_path, range, metadata.len(), headers);
+    }
+
+    Ok(HttpResponse {
+        status: HttpStatus::OK,
+        headers,
+        body: Some(HttpBody::File(std::path::PathBuf::from(&file_path))),
+    })
+}
+
+fn compute_etag(size: u64, last_modified: std::time::SystemTime) -> Result<String> {
+    let mtime = last_modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("File modification time is before the epoch")?
+        .as_secs();
+    Ok(format!("W/\"{}-{}\"", size, mtime))
+}
+
+fn not_modified(
+    request: &HttpRequest,
+    etag: &str,
+    last_modified: std::time::SystemTime,
+) -> bool {
+    if let Some(if_none_match) = request.headers.get("if-none-match") {
+        return if_none_match == "*"
+            || if_none_match.split(',').any(|candidate| candidate.trim() == etag);
+    }
+
+    if let Some(if_modified_since) = request.headers.get("if-modified-since") {
+        if let Ok(since) = httpdate::parse_http_date(if_modified_since) {
+            // HTTP-dates only have second resolution, but `last_modified` carries
+            // sub-second precision from the filesystem - round-trip it through the
+            // same formatter/parser so a client echoing back the exact
+            // `Last-Modified` we sent compares equal instead of "after".
+            let last_modified = httpdate::parse_http_date(&httpdate::fmt_http_date(last_modified))
+                .unwrap_or(last_modified);
+            return last_modified <= since;
+        }
+    }
+
+    false
+}
+
+fn handle_range(
+    file_path: &str,
+    range: &str,
+    total_len: u64,
+    mut headers: HttpHeaders,
+) -> Result<HttpResponse> {
+    let total_len = total_len as usize;
+
+    let bounds = range
+        .strip_prefix("bytes=")
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, end)| parse_range_bounds(start, end, total_len));
+
+    let (start, end) = match bounds {
+        Some(bounds) => bounds,
+        None => {
+            headers.insert(
+                "Content-Range".to_string(),
+                format!("bytes */{}", total_len),
+            );
+            return Ok(HttpResponse {
+                status: HttpStatus::RANGE_NOT_SATISFIABLE,
+                headers,
+                body: None,
+            });
+        }
+    };
+
+    let mut file = std::fs::File::open(file_path).context("Failed to open file")?;
+    file.seek(SeekFrom::Start(start as u64))
+        .context("Failed to seek file")?;
+    let mut slice = vec![0; end - start + 1];
+    file.read_exact(&mut slice).context("Failed to read range")?;
+
+    headers.insert(
+        "Content-Range".to_string(),
+        format!("bytes {}-{}/{}", start, end, total_len),
+    );
+
+    Ok(HttpResponse {
+        status: HttpStatus::PARTIAL_CONTENT,
+        headers,
+        body: Some(HttpBody::Binary(slice)),
+    })
+}
+
+fn parse_range_bounds(start: &str, end: &str, total_len: usize) -> Option<(usize, usize)> {
+    if total_len == 0 {
+        return None;
+    }
+
+    // A missing start (`bytes=-500`) is a suffix-range: the last N bytes of the file.
+    if start.is_empty() {
+        if end.is_empty() {
+            return None;
         }
+        let suffix_len: usize = end.parse().ok()?;
+        if suffix_len == 0 {
+            return None;
+        }
+        let suffix_len = suffix_len.min(total_len);
+        return Some((total_len - suffix_len, total_len - 1));
+    }
+
+    let start: usize = start.parse().ok()?;
+    let end: usize = if end.is_empty() {
+        total_len - 1
+    } else {
+        end.parse().ok()?
+    };
+
+    if start > end || end >= total_len {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+fn determine_file_content_type(file_name: &str) -> String {
+    let extension = std::path::Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "txt" => "text/plain",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
     }
+    .to_string()
 }
 
 fn handle_get_user_agent(request: &HttpRequest) -> Result<HttpResponse> {